@@ -0,0 +1,96 @@
+// Copyright (c) Aptos
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::move_vm_ext::MoveResolverExt;
+use move_deps::{
+    move_binary_format::errors::{Location, VMResult},
+    move_core_types::effects::{ChangeSet, Event},
+    move_table_extension::{NativeTableContext, TableChangeSet},
+    move_vm_runtime::{native_extensions::NativeContextExtensions, session::Session},
+};
+
+/// A thin wrapper around `move_vm_runtime::Session` that adds the effects
+/// this crate's natives (the table extension, in particular) need on top of
+/// what a plain Move session produces.
+pub struct SessionExt<'r, 'l, S> {
+    inner: Session<'r, 'l, S>,
+    // Mirrors the `MoveVmExtConfig::enable_table_extension` flag the session
+    // was created with, so `finish` only looks for a `NativeTableContext`
+    // when `MoveVmExt::new_session` actually registered one.
+    has_table_extension: bool,
+}
+
+impl<'r, 'l, S> SessionExt<'r, 'l, S>
+where
+    S: MoveResolverExt,
+{
+    pub fn new(inner: Session<'r, 'l, S>, has_table_extension: bool) -> Self {
+        Self {
+            inner,
+            has_table_extension,
+        }
+    }
+
+    /// Finishes the session, folding its resource/module `ChangeSet`, the
+    /// events it emitted, and the table changes accumulated in its
+    /// `NativeTableContext` (if one was registered) into a single
+    /// `SessionOutput`. This replaces the old pattern of callers
+    /// destructuring `Session::finish`'s tuple and separately asserting
+    /// events were empty wherever table changes needed to be merged in by
+    /// hand.
+    pub fn finish(self) -> VMResult<SessionOutput> {
+        let (change_set, events, mut extensions) = self.inner.finish_with_extensions()?;
+        let table_change_set = extract_table_change_set(self.has_table_extension, &mut extensions)?;
+
+        Ok(SessionOutput {
+            change_set,
+            events,
+            table_change_set,
+        })
+    }
+}
+
+/// Pulls the `TableChangeSet` accumulated in `extensions`' `NativeTableContext`
+/// out of it. The table changes are returned as a `TableChangeSet`; they are
+/// not converted into or merged with the resource `ChangeSet`. Returns the empty `TableChangeSet` without touching `extensions`
+/// when `has_table_extension` is `false`, since `MoveVmExt::new_session` never
+/// registered a `NativeTableContext` in that case and removing one that isn't
+/// there panics.
+fn extract_table_change_set(
+    has_table_extension: bool,
+    extensions: &mut NativeContextExtensions<'_>,
+) -> VMResult<TableChangeSet> {
+    if !has_table_extension {
+        return Ok(TableChangeSet::default());
+    }
+
+    let table_context: NativeTableContext = extensions.remove();
+    table_context
+        .into_change_set()
+        .map_err(|e| e.finish(Location::Undefined))
+}
+
+/// The combined effects of running a `SessionExt` to completion: the
+/// resource/module changeset, the events it emitted, and the table changes
+/// produced by its `NativeTableContext`. One effects value replaces the
+/// several tuples and contexts callers previously had to pull apart by hand.
+pub struct SessionOutput {
+    pub change_set: ChangeSet,
+    pub events: Vec<Event>,
+    pub table_change_set: TableChangeSet,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_disabled_session_finishes_without_pulling_a_table_context() {
+        // No `NativeTableContext` is registered here, mirroring what
+        // `MoveVmExt::new_session` leaves in place when
+        // `enable_table_extension` is `false`. `extract_table_change_set` must
+        // not try to remove one and panic.
+        let mut extensions = NativeContextExtensions::default();
+        assert!(extract_table_change_set(false, &mut extensions).is_ok());
+    }
+}
@@ -9,29 +9,102 @@ use aptos_gas::NativeGasParameters;
 use framework::natives::{code::NativeCodeContext, transaction_context::NativeTransactionContext};
 use move_deps::{
     move_binary_format::errors::VMResult,
-    move_table_extension::NativeTableContext,
-    move_vm_runtime::{move_vm::MoveVM, native_extensions::NativeContextExtensions},
+    move_core_types::{
+        account_address::AccountAddress, identifier::Identifier, language_storage::CORE_CODE_ADDRESS,
+    },
+    move_table_extension::{self, NativeTableContext},
+    move_vm_runtime::{
+        move_vm::MoveVM,
+        native_extensions::NativeContextExtensions,
+        native_functions::NativeFunctionTable,
+    },
 };
-use std::ops::Deref;
+use once_cell::sync::Lazy;
+use std::{any::Any, collections::HashSet, ops::Deref, sync::Mutex};
+
+/// A hook that lets callers outside this crate attach their own native context
+/// extensions to every `NativeContextExtensions` this VM builds, mirroring the
+/// extension mechanism the unit-test framework uses to let test harnesses wire
+/// up custom native functions without forking the VM. Generic over the
+/// resolver type `S` so the hook can build resolver-backed contexts (e.g. a
+/// custom `NativeTableContext`) the same way the built-in extensions do.
+pub type ExtensionHook<S> = dyn Fn(&mut NativeContextExtensions<'_>, &S) + Send + Sync;
+
+// `EXTENSION_HOOK` can't name the resolver type `S` directly since `new_session`
+// is generic over it and a `static` can't carry an unresolved type parameter.
+// It is instead stored type-erased as `Any` and downcast back to
+// `Box<ExtensionHook<S>>` in `new_session`, so only a hook registered for the
+// resolver type actually in use takes effect.
+static EXTENSION_HOOK: Lazy<Mutex<Option<Box<dyn Any + Send + Sync>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Configuration for the native-extension set a `MoveVmExt` wires into every
+/// session it creates, following the `move-table-extension` integration
+/// pattern of letting callers vary the deployment address of extension-backed
+/// natives instead of hard-coding `CORE_CODE_ADDRESS` everywhere.
+#[derive(Clone, Debug)]
+pub struct MoveVmExtConfig {
+    /// The address the standard library and framework natives are deployed
+    /// under.
+    pub natives_address: AccountAddress,
+    /// The address the table-extension natives are deployed under. Only
+    /// consulted when `enable_table_extension` is set.
+    pub table_extension_address: AccountAddress,
+    /// Whether to register the table natives and `NativeTableContext` at all.
+    /// Lighter VM instances (e.g. for tooling or non-table workloads) can set
+    /// this to `false` to skip the table subsystem entirely.
+    pub enable_table_extension: bool,
+}
+
+impl Default for MoveVmExtConfig {
+    fn default() -> Self {
+        Self {
+            natives_address: CORE_CODE_ADDRESS,
+            table_extension_address: CORE_CODE_ADDRESS,
+            enable_table_extension: true,
+        }
+    }
+}
 
 pub struct MoveVmExt {
     inner: MoveVM,
+    config: MoveVmExtConfig,
 }
 
 impl MoveVmExt {
     pub fn new(native_gas_params: NativeGasParameters) -> VMResult<Self> {
+        Self::new_with_config(native_gas_params, MoveVmExtConfig::default())
+    }
+
+    pub fn new_with_config(
+        native_gas_params: NativeGasParameters,
+        config: MoveVmExtConfig,
+    ) -> VMResult<Self> {
         Ok(Self {
-            inner: MoveVM::new(aptos_natives(native_gas_params))?,
+            inner: MoveVM::new(natives_for_config(native_gas_params, &config))?,
+            config,
         })
     }
 
-    pub fn new_session<'r, S: MoveResolverExt>(
+    /// Registers a hook that `new_session` invokes, after adding the built-in
+    /// extensions, for every session this VM creates whose resolver type is
+    /// `S`. This allows alternative Move environments (and programmatic
+    /// unit-test callers) to inject extra native contexts at both VM-startup
+    /// and per-session time without touching core VM code. Registering a new
+    /// hook replaces the previous one.
+    pub fn set_extension_hook<S: 'static>(hook: Box<ExtensionHook<S>>) {
+        *EXTENSION_HOOK.lock().unwrap() = Some(Box::new(hook));
+    }
+
+    pub fn new_session<'r, S: MoveResolverExt + 'static>(
         &self,
         remote: &'r S,
         session_id: SessionId,
     ) -> SessionExt<'r, '_, S> {
         let mut extensions = NativeContextExtensions::default();
-        extensions.add(NativeTableContext::new(session_id.as_uuid(), remote));
+        if self.config.enable_table_extension {
+            extensions.add(NativeTableContext::new(session_id.as_uuid(), remote));
+        }
 
         let script_hash = match session_id {
             SessionId::Txn {
@@ -44,8 +117,80 @@ impl MoveVmExt {
         extensions.add(NativeTransactionContext::new(script_hash));
         extensions.add(NativeCodeContext::default());
 
-        SessionExt::new(self.inner.new_session_with_extensions(remote, extensions))
+        invoke_extension_hook(&mut extensions, remote);
+
+        SessionExt::new(
+            self.inner.new_session_with_extensions(remote, extensions),
+            self.config.enable_table_extension,
+        )
+    }
+}
+
+/// Runs the registered extension hook for resolver type `S` against
+/// `extensions`, if one is registered for that type. Takes the hook out of
+/// `EXTENSION_HOOK` and puts it back afterwards instead of holding the mutex
+/// across the call, since the hook is expected to build resolver-backed
+/// contexts and may itself start a session (or register a new hook) —
+/// holding the guard across the call would deadlock on re-entry.
+fn invoke_extension_hook<S: 'static>(extensions: &mut NativeContextExtensions<'_>, remote: &S) {
+    let hook = EXTENSION_HOOK.lock().unwrap().take();
+    if let Some(erased) = &hook {
+        if let Some(typed) = erased.downcast_ref::<Box<ExtensionHook<S>>>() {
+            typed(extensions, remote);
+        }
     }
+    if let Some(erased) = hook {
+        // Only restore the hook if it didn't register a replacement for itself.
+        let mut slot = EXTENSION_HOOK.lock().unwrap();
+        if slot.is_none() {
+            *slot = Some(erased);
+        }
+    }
+}
+
+/// The `(module, function)` identifiers `move_table_extension` registers its
+/// natives under. Computed from `move_table_extension::table_natives` itself
+/// (with throwaway gas params, since only the identifiers are used) rather
+/// than a hard-coded module-name literal, so `natives_for_config` keeps
+/// recognizing the table natives even if that crate ever renames its module.
+fn table_native_identifiers() -> HashSet<(Identifier, Identifier)> {
+    move_table_extension::table_natives(
+        CORE_CODE_ADDRESS,
+        move_table_extension::GasParameters::zeros(),
+    )
+    .into_iter()
+    .map(|(_, module_name, func_name, _)| (module_name, func_name))
+    .collect()
+}
+
+/// Assembles the native-function table `MoveVmExt::new_with_config` registers
+/// with the inner `MoveVM`, applying `config`'s deployment addresses and
+/// consulting `enable_table_extension` so that VM instances built with it
+/// disabled never see the table natives registered in the first place —
+/// matching `new_session` leaving out their `NativeTableContext`.
+fn natives_for_config(
+    native_gas_params: NativeGasParameters,
+    config: &MoveVmExtConfig,
+) -> NativeFunctionTable {
+    let table_natives = table_native_identifiers();
+
+    aptos_natives(native_gas_params)
+        .into_iter()
+        .filter_map(|(module_address, module_name, func_name, func)| {
+            let is_table_native = table_natives.contains(&(module_name.clone(), func_name.clone()));
+            if is_table_native {
+                return config
+                    .enable_table_extension
+                    .then(|| (config.table_extension_address, module_name, func_name, func));
+            }
+            let module_address = if module_address == CORE_CODE_ADDRESS {
+                config.natives_address
+            } else {
+                module_address
+            };
+            Some((module_address, module_name, func_name, func))
+        })
+        .collect()
 }
 
 impl Deref for MoveVmExt {
@@ -55,3 +200,65 @@ impl Deref for MoveVmExt {
         &self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // `invoke_extension_hook` is generic only over `'static`, so a programmatic
+    // unit-test caller can exercise it without a real `MoveResolverExt`.
+    #[test]
+    fn registered_hook_runs_against_matching_resolver_type() {
+        static CALLED: AtomicBool = AtomicBool::new(false);
+        MoveVmExt::set_extension_hook::<u8>(Box::new(|_extensions, _remote| {
+            CALLED.store(true, Ordering::SeqCst);
+        }));
+
+        let mut extensions = NativeContextExtensions::default();
+        invoke_extension_hook(&mut extensions, &0u8);
+        assert!(CALLED.load(Ordering::SeqCst));
+
+        // The hook must still be registered afterwards: invoking it doesn't
+        // consume it, and the mutex guard must not have been left held.
+        CALLED.store(false, Ordering::SeqCst);
+        invoke_extension_hook(&mut extensions, &0u8);
+        assert!(CALLED.load(Ordering::SeqCst));
+
+        // A hook registered for `u8` must not fire for an unrelated resolver
+        // type.
+        CALLED.store(false, Ordering::SeqCst);
+        invoke_extension_hook(&mut extensions, &"not a u8 resolver".to_string());
+        assert!(!CALLED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn hook_can_reenter_by_registering_a_new_hook() {
+        // A hook that itself calls `set_extension_hook` must not deadlock,
+        // which it would if `invoke_extension_hook` held the `EXTENSION_HOOK`
+        // mutex across the hook call.
+        MoveVmExt::set_extension_hook::<u8>(Box::new(|_extensions, _remote| {
+            MoveVmExt::set_extension_hook::<u8>(Box::new(|_extensions, _remote| {}));
+        }));
+
+        let mut extensions = NativeContextExtensions::default();
+        invoke_extension_hook(&mut extensions, &0u8);
+    }
+
+    #[test]
+    fn disabling_table_extension_drops_the_table_natives() {
+        let table_natives = table_native_identifiers();
+        assert!(!table_natives.is_empty());
+
+        let config = MoveVmExtConfig {
+            enable_table_extension: false,
+            ..MoveVmExtConfig::default()
+        };
+        let natives = natives_for_config(NativeGasParameters::zeros(), &config);
+
+        assert!(natives
+            .into_iter()
+            .all(|(_, module_name, func_name, _)| !table_natives
+                .contains(&(module_name, func_name))));
+    }
+}